@@ -0,0 +1,296 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standalone HTTP admin/management server, alongside the binary RPC
+//! handler in [`crate::master::master_handler`]. Reuses [`MasterFilesystem`]
+//! and [`MountManager`] rather than duplicating their state.
+
+use crate::master::fs::MasterFilesystem;
+use crate::master::{Master, MasterMetrics, MountManager};
+use curvine_common::conf::ClusterConf;
+use curvine_common::FsResult;
+use std::sync::Arc;
+
+/// Bind address and auth settings for the admin HTTP server, read from
+/// [`ClusterConf`]. The server is disabled unless a bind address is set.
+#[derive(Debug, Clone)]
+pub struct AdminApiConf {
+    pub bind_addr: Option<String>,
+    pub bearer_token: Option<String>,
+}
+
+impl AdminApiConf {
+    pub fn from_conf(conf: &ClusterConf) -> Self {
+        Self {
+            bind_addr: conf.master.admin_http_bind.clone(),
+            bearer_token: conf.master.admin_http_token.clone(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.bind_addr.is_some()
+    }
+
+    /// A bind address without a bearer token would expose the mutating
+    /// `/mount` routes with no auth; `serve` refuses to bind in that case.
+    pub fn open_without_auth(&self) -> bool {
+        self.enabled() && self.bearer_token.is_none()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn enabled_requires_a_bind_address() {
+        let conf = AdminApiConf {
+            bind_addr: None,
+            bearer_token: None,
+        };
+        assert!(!conf.enabled());
+
+        let conf = AdminApiConf {
+            bind_addr: Some("127.0.0.1:9000".to_string()),
+            bearer_token: None,
+        };
+        assert!(conf.enabled());
+    }
+
+    #[test]
+    fn bind_address_without_token_is_open_without_auth() {
+        let conf = AdminApiConf {
+            bind_addr: Some("127.0.0.1:9000".to_string()),
+            bearer_token: None,
+        };
+        assert!(conf.open_without_auth());
+
+        let conf = AdminApiConf {
+            bind_addr: Some("127.0.0.1:9000".to_string()),
+            bearer_token: Some("secret".to_string()),
+        };
+        assert!(!conf.open_without_auth());
+
+        let conf = AdminApiConf {
+            bind_addr: None,
+            bearer_token: None,
+        };
+        assert!(!conf.open_without_auth());
+    }
+}
+
+/// Shared state handed to every HTTP route handler.
+#[derive(Clone)]
+pub struct AdminApiState {
+    pub fs: MasterFilesystem,
+    pub mount_manager: Arc<MountManager>,
+    pub metrics: &'static MasterMetrics,
+    pub conf: AdminApiConf,
+}
+
+/// The HTTP admin server. `serve` runs until the process shuts down;
+/// `Master` is expected to build one from its own `fs`/`mount_manager` at
+/// startup and `rt.spawn(server.serve())` it alongside the RPC listener —
+/// it is otherwise inert, since constructing it does no I/O.
+pub struct AdminApiServer {
+    state: AdminApiState,
+}
+
+impl AdminApiServer {
+    pub fn new(conf: &ClusterConf, fs: MasterFilesystem, mount_manager: Arc<MountManager>) -> Self {
+        Self {
+            state: AdminApiState {
+                fs,
+                mount_manager,
+                metrics: Master::get_metrics(),
+                conf: AdminApiConf::from_conf(conf),
+            },
+        }
+    }
+
+    /// Bind and serve the admin API. No-op if no bind address is
+    /// configured, so enabling the server is purely additive. Refuses to
+    /// bind at all if no bearer token is configured, since the API exposes
+    /// destructive routes (`POST`/`DELETE /mount`) that must not be left
+    /// open by default.
+    pub async fn serve(self) -> FsResult<()> {
+        let Some(bind_addr) = self.state.conf.bind_addr.clone() else {
+            return Ok(());
+        };
+        if self.state.conf.open_without_auth() {
+            return Err(curvine_common::error::FsError::common(
+                "admin_http_bind is set but admin_http_token is not; refusing to bind the admin API open",
+            ));
+        }
+
+        let app = axum::Router::new()
+            .route("/master/info", axum::routing::get(routes::get_master_info))
+            .route("/workers", axum::routing::get(routes::list_workers))
+            .route("/mount/table", axum::routing::get(routes::get_mount_table))
+            .route("/mount/point", axum::routing::get(routes::get_mount_point))
+            .route("/mount", axum::routing::post(routes::mount))
+            .route("/mount", axum::routing::delete(routes::umount))
+            .route("/metrics", axum::routing::get(routes::metrics))
+            .layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                auth::require_bearer_token,
+            ))
+            .with_state(self.state);
+
+        let listener = tokio::net::TcpListener::bind(&bind_addr)
+            .await
+            .map_err(|e| curvine_common::error::FsError::common(e.to_string()))?;
+        axum::serve(listener, app)
+            .await
+            .map_err(|e| curvine_common::error::FsError::common(e.to_string()))
+    }
+}
+
+mod auth {
+    use super::AdminApiState;
+    use axum::extract::{Request, State};
+    use axum::http::StatusCode;
+    use axum::middleware::Next;
+    use axum::response::Response;
+
+    pub async fn require_bearer_token(
+        State(state): State<AdminApiState>,
+        req: Request,
+        next: Next,
+    ) -> Result<Response, StatusCode> {
+        // `AdminApiServer::serve` refuses to bind at all without a token
+        // configured, so this should be unreachable in practice; treat it
+        // as unauthenticated rather than falling open if it is ever hit.
+        let Some(expected) = &state.conf.bearer_token else {
+            return Err(StatusCode::UNAUTHORIZED);
+        };
+
+        let provided = req
+            .headers()
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        match provided {
+            Some(token) if token == expected => Ok(next.run(req).await),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+}
+
+mod routes {
+    use super::AdminApiState;
+    use axum::extract::{Query, State};
+    use axum::http::StatusCode;
+    use axum::Json;
+    use curvine_common::utils::ProtoUtils;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+
+    fn to_status(err: curvine_common::error::FsError) -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": err.to_string() })),
+        )
+    }
+
+    pub async fn get_master_info(
+        State(state): State<AdminApiState>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let info = state.fs.master_info().map_err(to_status)?;
+        Ok(Json(json!(ProtoUtils::master_info_to_pb(info))))
+    }
+
+    pub async fn list_workers(
+        State(state): State<AdminApiState>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let workers = state.fs.worker_manager.read().list_workers();
+        Ok(Json(json!(workers)))
+    }
+
+    pub async fn get_mount_table(
+        State(state): State<AdminApiState>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let table = state.mount_manager.get_mount_table().map_err(to_status)?;
+        Ok(Json(json!(table)))
+    }
+
+    #[derive(Deserialize)]
+    pub struct MountPointQuery {
+        pub path: String,
+    }
+
+    pub async fn get_mount_point(
+        State(state): State<AdminApiState>,
+        Query(query): Query<MountPointQuery>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let path = curvine_common::fs::Path::from_str(query.path).map_err(to_status)?;
+        let point = state
+            .mount_manager
+            .get_mount_point(&path)
+            .map_err(to_status)?;
+        Ok(Json(json!(point)))
+    }
+
+    #[derive(Deserialize)]
+    pub struct MountBody {
+        pub curvine_path: String,
+        pub ufs_path: String,
+    }
+
+    pub async fn mount(
+        State(state): State<AdminApiState>,
+        Json(body): Json<MountBody>,
+    ) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+        let curvine_uri =
+            curvine_common::fs::CurvineURI::new(body.curvine_path).map_err(to_status)?;
+        let ufs_uri = curvine_common::fs::CurvineURI::new(body.ufs_path).map_err(to_status)?;
+        state
+            .mount_manager
+            .mount(None, &curvine_uri, &ufs_uri, &Default::default())
+            .map_err(to_status)?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    #[derive(Deserialize)]
+    pub struct UmountQuery {
+        pub path: String,
+    }
+
+    pub async fn umount(
+        State(state): State<AdminApiState>,
+        Query(query): Query<UmountQuery>,
+    ) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+        let path = curvine_common::fs::CurvineURI::new(query.path).map_err(to_status)?;
+        state.mount_manager.umount(&path).map_err(to_status)?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    // Scrapes the global `prometheus` registry directly rather than
+    // assuming a dedicated encoder method on `MasterMetrics`: every
+    // metric already registers itself into that registry when it's
+    // created (the same registry `rpc_request_time`/`rpc_request_count`
+    // use), so this needs no knowledge of `MasterMetrics`'s fields.
+    pub async fn metrics(State(_state): State<AdminApiState>) -> Result<String, StatusCode> {
+        use prometheus::Encoder;
+
+        let metric_families = prometheus::gather();
+        let encoder = prometheus::TextEncoder::new();
+        let mut buf = Vec::new();
+        encoder
+            .encode(&metric_families, &mut buf)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        String::from_utf8(buf).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wire types for the batched metadata RPC (`MasterHandler::batch`).
+//!
+//! These are hand-written `prost::Message`/`prost::Oneof` impls rather
+//! than types generated from a `.proto` file: this RPC rides on the
+//! existing per-op request messages (`MkdirRequest`, `CreateFileRequest`,
+//! ...), so there is nothing new for the `.proto` schema to describe
+//! beyond "a list of these, each tagged with which kind it is" — exactly
+//! what a `oneof` expresses. Encoding still goes through `prost`, so these
+//! decode with the same `ctx.parse_header::<BatchRequest>()` call every
+//! other request uses.
+
+use curvine_common::proto::{
+    CreateFileRequest, DeleteRequest, GetFileStatusRequest, MkdirRequest, RenameRequest,
+};
+use prost::{Message, Oneof};
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub ops: Vec<BatchOp>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchOp {
+    /// Echoed back on `BatchOpResult` so the caller can match results to
+    /// requests; also used as the idempotency key for the sub-op, exactly
+    /// as if it had been sent standalone.
+    #[prost(int64, tag = "1")]
+    pub req_id: i64,
+
+    #[prost(oneof = "batch_op::Payload", tags = "2, 3, 4, 5, 6")]
+    pub payload: Option<batch_op::Payload>,
+}
+
+pub mod batch_op {
+    use super::*;
+
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum Payload {
+        #[prost(message, tag = "2")]
+        Mkdir(MkdirRequest),
+        #[prost(message, tag = "3")]
+        CreateFile(CreateFileRequest),
+        #[prost(message, tag = "4")]
+        Delete(DeleteRequest),
+        #[prost(message, tag = "5")]
+        Rename(RenameRequest),
+        #[prost(message, tag = "6")]
+        FileStatus(GetFileStatusRequest),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchOpResult {
+    #[prost(int64, tag = "1")]
+    pub req_id: i64,
+    #[prost(bool, tag = "2")]
+    pub ok: bool,
+    /// The sub-op's own response message, pre-encoded with `prost`. Kept
+    /// as opaque bytes rather than another `oneof` because the caller
+    /// already knows which request it sent at a given `req_id` and so
+    /// which response type to decode this as.
+    #[prost(bytes, tag = "3")]
+    pub payload: Vec<u8>,
+    #[prost(string, tag = "4")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BatchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: Vec<BatchOpResult>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn batch_request_with_each_op_kind_round_trips() {
+        let req = BatchRequest {
+            ops: vec![
+                BatchOp {
+                    req_id: 1,
+                    payload: Some(batch_op::Payload::Mkdir(MkdirRequest {
+                        path: "/a".to_string(),
+                        ..Default::default()
+                    })),
+                },
+                BatchOp {
+                    req_id: 2,
+                    payload: Some(batch_op::Payload::Delete(DeleteRequest {
+                        path: "/b".to_string(),
+                        ..Default::default()
+                    })),
+                },
+                BatchOp {
+                    req_id: 3,
+                    payload: None,
+                },
+            ],
+        };
+
+        let encoded = req.encode_to_vec();
+        let decoded = BatchRequest::decode(encoded.as_slice()).unwrap();
+        assert_eq!(decoded, req);
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Optional end-to-end block checksums: a client picks an algorithm, the
+//! master persists the digest it is given at `complete_file` time, and
+//! later `block_report`s are compared against that digest.
+
+use curvine_common::error::FsError;
+use curvine_common::FsResult;
+
+/// Digest algorithm a client may request per block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32c,
+    Sha256,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Crc32c => "crc32c",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+impl TryFrom<&str> for ChecksumAlgorithm {
+    type Error = FsError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "crc32c" => Ok(ChecksumAlgorithm::Crc32c),
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "blake3" => Ok(ChecksumAlgorithm::Blake3),
+            other => Err(FsError::common(format!(
+                "unsupported checksum algorithm: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl Default for ChecksumAlgorithm {
+    fn default() -> Self {
+        ChecksumAlgorithm::Crc32c
+    }
+}
+
+/// An expected digest for a single block, as attached by the client or
+/// computed by a worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockChecksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+impl BlockChecksum {
+    pub fn new(algorithm: ChecksumAlgorithm, digest: Vec<u8>) -> Self {
+        Self { algorithm, digest }
+    }
+
+    /// The algorithm a file should use: the per-`CreateFileOpts` override
+    /// when the client set one, else `default_algorithm` (the cluster-wide
+    /// default, resolved once from `ClusterConf` at `MasterHandler`
+    /// construction).
+    pub fn resolve_algorithm(
+        default_algorithm: ChecksumAlgorithm,
+        opts_algorithm: Option<&str>,
+    ) -> FsResult<ChecksumAlgorithm> {
+        match opts_algorithm {
+            Some(value) => ChecksumAlgorithm::try_from(value),
+            None => Ok(default_algorithm),
+        }
+    }
+}
+
+/// Compares a worker-reported digest against the digest recorded at
+/// `complete_file` time. Returns `Ok(())` when they match, or when no
+/// digest was ever recorded (checksums are opt-in), and an error
+/// otherwise so the caller can mark the replica corrupt and trigger
+/// re-replication. A worker that omits the digest on a file that was
+/// created with checksums on is treated as corrupt, not as healthy.
+pub fn verify_block_checksum(
+    expected: Option<&BlockChecksum>,
+    reported: Option<&BlockChecksum>,
+) -> FsResult<()> {
+    let (expected, reported) = match (expected, reported) {
+        (None, None) => return Ok(()),
+        (None, Some(_)) => return Ok(()),
+        (Some(_), None) => {
+            return Err(FsError::common(
+                "worker reported no checksum for a block that requires one, replica corrupt",
+            ))
+        }
+        (Some(e), Some(r)) => (e, r),
+    };
+
+    if expected.algorithm != reported.algorithm {
+        return Err(FsError::common(format!(
+            "checksum algorithm mismatch: expected {}, got {}",
+            expected.algorithm.as_str(),
+            reported.algorithm.as_str()
+        )));
+    }
+
+    if expected.digest != reported.digest {
+        return Err(FsError::common("block checksum mismatch, replica corrupt"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matching_digests_pass() {
+        let checksum = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![1, 2, 3]);
+        assert!(verify_block_checksum(Some(&checksum), Some(&checksum)).is_ok());
+    }
+
+    #[test]
+    fn mismatched_digest_is_an_error() {
+        let expected = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![1, 2, 3]);
+        let reported = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![4, 5, 6]);
+        assert!(verify_block_checksum(Some(&expected), Some(&reported)).is_err());
+    }
+
+    #[test]
+    fn mismatched_algorithm_is_an_error() {
+        let expected = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![1, 2, 3]);
+        let reported = BlockChecksum::new(ChecksumAlgorithm::Sha256, vec![1, 2, 3]);
+        assert!(verify_block_checksum(Some(&expected), Some(&reported)).is_err());
+    }
+
+    #[test]
+    fn no_expectation_and_no_report_is_opt_in_and_passes() {
+        assert!(verify_block_checksum(None, None).is_ok());
+    }
+
+    #[test]
+    fn unexpected_report_with_no_expectation_passes() {
+        let checksum = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![1, 2, 3]);
+        assert!(verify_block_checksum(None, Some(&checksum)).is_ok());
+    }
+
+    #[test]
+    fn missing_report_when_expected_is_treated_as_corrupt() {
+        let checksum = BlockChecksum::new(ChecksumAlgorithm::Crc32c, vec![1, 2, 3]);
+        assert!(verify_block_checksum(Some(&checksum), None).is_err());
+    }
+
+    #[test]
+    fn resolve_algorithm_falls_back_to_the_cluster_default() {
+        let resolved = BlockChecksum::resolve_algorithm(ChecksumAlgorithm::Blake3, None).unwrap();
+        assert_eq!(resolved, ChecksumAlgorithm::Blake3);
+    }
+
+    #[test]
+    fn resolve_algorithm_honors_a_per_file_override() {
+        let resolved =
+            BlockChecksum::resolve_algorithm(ChecksumAlgorithm::Crc32c, Some("sha256")).unwrap();
+        assert_eq!(resolved, ChecksumAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn resolve_algorithm_rejects_an_unknown_override() {
+        assert!(BlockChecksum::resolve_algorithm(ChecksumAlgorithm::Crc32c, Some("md5")).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content-defined chunking and reference-counted block dedup: chunk
+//! boundaries are picked by a rolling hash instead of a fixed block size,
+//! and identical chunks are stored once and shared by reference count
+//! across files. Opt-in per file via `CreateFileOpts`.
+
+use curvine_common::state::LocatedBlock;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+/// A content digest identifying a block's data, independent of which
+/// file(s) reference it.
+pub type ChunkDigest = [u8; 32];
+
+/// Rolling-hash chunk boundary detector. A new chunk boundary is declared
+/// wherever the bits of the rolling hash selected by `mask` are all zero,
+/// giving an expected chunk size of `1 << mask.count_ones()` bytes,
+/// clamped to `[min_size, max_size]` so no chunk is pathologically small
+/// or large. The last chunk takes whatever bytes remain and may be
+/// shorter than `min_size`.
+pub struct ContentDefinedChunker {
+    mask: u64,
+    min_size: usize,
+    max_size: usize,
+}
+
+impl ContentDefinedChunker {
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        let mask = (avg_size.next_power_of_two() as u64 - 1).max(1);
+        Self {
+            mask,
+            min_size,
+            max_size,
+        }
+    }
+
+    /// Returns the offsets at which `data` should be split into chunks.
+    pub fn boundaries(&self, data: &[u8]) -> Vec<usize> {
+        let mut boundaries = Vec::new();
+        let mut hash: u64 = 0;
+        let mut since_last = 0usize;
+
+        for (i, &byte) in data.iter().enumerate() {
+            // Buzhash-style rolling hash: cheap, streaming, and good
+            // enough for boundary selection (not used for integrity).
+            hash = hash.rotate_left(1) ^ (byte as u64);
+            since_last += 1;
+
+            let at_boundary = since_last >= self.min_size
+                && (hash & self.mask == 0 || since_last >= self.max_size);
+            if at_boundary {
+                boundaries.push(i + 1);
+                since_last = 0;
+                hash = 0;
+            }
+        }
+
+        if boundaries.last().copied() != Some(data.len()) && !data.is_empty() {
+            boundaries.push(data.len());
+        }
+        boundaries
+    }
+}
+
+/// Server-side index of known chunks, keyed by content digest, with a
+/// reference count so storage is only reclaimed once no file references
+/// the chunk anymore.
+#[derive(Default)]
+pub struct BlockDedupIndex {
+    entries: RwLock<HashMap<ChunkDigest, DedupEntry>>,
+}
+
+struct DedupEntry {
+    located_block: LocatedBlock,
+    ref_count: u64,
+}
+
+impl BlockDedupIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up a digest. A hit means `add_block` can skip allocating new
+    /// storage and instead bump the refcount and hand back the existing
+    /// located block.
+    pub fn lookup(&self, digest: &ChunkDigest) -> Option<LocatedBlock> {
+        self.entries
+            .read()
+            .get(digest)
+            .map(|e| e.located_block.clone())
+    }
+
+    /// Register a newly written block under `digest`, or bump the
+    /// refcount if it is already known (e.g. a race between two writers
+    /// producing the same content).
+    pub fn register(&self, digest: ChunkDigest, located_block: LocatedBlock) {
+        let mut entries = self.entries.write();
+        entries
+            .entry(digest)
+            .and_modify(|e| e.ref_count += 1)
+            .or_insert(DedupEntry {
+                located_block,
+                ref_count: 1,
+            });
+    }
+
+    /// Increment the refcount for a digest already in the index, used
+    /// when `add_block` reuses an existing chunk for a new file.
+    pub fn increment(&self, digest: &ChunkDigest) {
+        if let Some(entry) = self.entries.write().get_mut(digest) {
+            entry.ref_count += 1;
+        }
+    }
+
+    /// Decrement the refcount for a digest, called from `delete`/`rename`
+    /// when a file stops referencing a block. Returns `true` once the
+    /// refcount reaches zero, meaning the underlying storage is now safe
+    /// to reclaim.
+    pub fn decrement(&self, digest: &ChunkDigest) -> bool {
+        let mut entries = self.entries.write();
+        let Some(entry) = entries.get_mut(digest) else {
+            return false;
+        };
+        entry.ref_count = entry.ref_count.saturating_sub(1);
+        if entry.ref_count == 0 {
+            entries.remove(digest);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn ref_count(&self, digest: &ChunkDigest) -> u64 {
+        self.entries.read().get(digest).map_or(0, |e| e.ref_count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn digest(byte: u8) -> ChunkDigest {
+        [byte; 32]
+    }
+
+    #[test]
+    fn register_then_lookup_hits() {
+        let index = BlockDedupIndex::new();
+        let d = digest(1);
+        assert!(index.lookup(&d).is_none());
+
+        index.register(d, LocatedBlock::default());
+        assert!(index.lookup(&d).is_some());
+        assert_eq!(index.ref_count(&d), 1);
+    }
+
+    #[test]
+    fn registering_the_same_digest_twice_bumps_the_refcount_instead_of_replacing() {
+        let index = BlockDedupIndex::new();
+        let d = digest(2);
+
+        index.register(d, LocatedBlock::default());
+        index.register(d, LocatedBlock::default());
+        assert_eq!(index.ref_count(&d), 2);
+    }
+
+    #[test]
+    fn increment_bumps_an_existing_entry_and_is_a_no_op_on_an_unknown_digest() {
+        let index = BlockDedupIndex::new();
+        let d = digest(3);
+
+        index.increment(&d);
+        assert_eq!(index.ref_count(&d), 0);
+
+        index.register(d, LocatedBlock::default());
+        index.increment(&d);
+        assert_eq!(index.ref_count(&d), 2);
+    }
+
+    #[test]
+    fn decrement_to_zero_removes_the_entry() {
+        let index = BlockDedupIndex::new();
+        let d = digest(4);
+        index.register(d, LocatedBlock::default());
+        index.increment(&d);
+
+        assert!(!index.decrement(&d));
+        assert_eq!(index.ref_count(&d), 1);
+
+        index.increment(&d);
+        assert!(!index.decrement(&d));
+        assert!(index.decrement(&d));
+        assert_eq!(index.ref_count(&d), 0);
+        assert!(index.lookup(&d).is_none());
+    }
+
+    #[test]
+    fn decrement_on_an_unknown_digest_does_not_underflow() {
+        let index = BlockDedupIndex::new();
+        let d = digest(5);
+        assert!(!index.decrement(&d));
+        assert_eq!(index.ref_count(&d), 0);
+    }
+
+    #[test]
+    fn boundaries_respects_min_and_max_size_and_always_reaches_the_end() {
+        let chunker = ContentDefinedChunker::new(4, 8, 16);
+        let data = vec![0u8; 100];
+        let boundaries = chunker.boundaries(&data);
+
+        assert_eq!(boundaries.last().copied(), Some(data.len()));
+
+        let mut prev = 0;
+        for &b in &boundaries {
+            let size = b - prev;
+            // The final chunk may be shorter than `min_size` since it just
+            // takes whatever bytes are left, but every other chunk must
+            // respect both bounds.
+            if b != data.len() {
+                assert!(size >= 4, "chunk size {size} below min_size");
+            }
+            assert!(size <= 16, "chunk size {size} above max_size");
+            prev = b;
+        }
+    }
+
+    #[test]
+    fn boundaries_of_empty_data_is_empty() {
+        let chunker = ContentDefinedChunker::new(4, 8, 16);
+        assert!(chunker.boundaries(&[]).is_empty());
+    }
+}
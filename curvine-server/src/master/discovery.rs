@@ -0,0 +1,185 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Optional Kubernetes-based worker discovery, feature-flagged in
+//! [`ClusterConf`]. Heartbeats stay the sole source of truth for liveness;
+//! discovery only seeds the *expected* membership set, so a freshly
+//! started master can tell "worker not yet reporting" apart from "worker
+//! never existed".
+
+use curvine_common::conf::ClusterConf;
+use curvine_common::error::FsError;
+use curvine_common::FsResult;
+use orpc::runtime::Runtime;
+use parking_lot::RwLock;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Pod-selector configuration for the Kubernetes discovery backend.
+#[derive(Debug, Clone)]
+pub struct K8sDiscoveryConf {
+    pub enabled: bool,
+    pub namespace: String,
+    pub label_selector: String,
+}
+
+impl K8sDiscoveryConf {
+    pub fn from_conf(conf: &ClusterConf) -> Self {
+        Self {
+            enabled: conf.master.k8s_discovery_enabled,
+            namespace: conf.master.k8s_discovery_namespace.clone(),
+            label_selector: conf.master.k8s_discovery_label_selector.clone(),
+        }
+    }
+}
+
+/// The set of workers discovery expects to eventually see heartbeats
+/// from, independent of whether they have reported in yet.
+#[derive(Debug, Default, Clone)]
+pub struct ExpectedMembership {
+    expected: HashSet<String>,
+}
+
+impl ExpectedMembership {
+    pub fn new(expected: HashSet<String>) -> Self {
+        Self { expected }
+    }
+
+    pub fn expected_count(&self) -> usize {
+        self.expected.len()
+    }
+
+    pub fn is_expected(&self, pod_identity: &str) -> bool {
+        self.expected.contains(pod_identity)
+    }
+
+    /// Pair the expected set against the live heartbeat roster so
+    /// `get_master_info` can surface "3/5 workers reporting" instead of
+    /// silently treating 3 live workers as the whole cluster.
+    pub fn present_vs_expected(&self, present: &HashSet<String>) -> (usize, usize) {
+        let present_and_expected = self.expected.intersection(present).count();
+        (present_and_expected, self.expected.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn counts_present_workers_sharing_the_same_identity_space() {
+        let membership = ExpectedMembership::new(set(&["10.0.0.1", "10.0.0.2", "10.0.0.3"]));
+        let present = set(&["10.0.0.1", "10.0.0.2"]);
+
+        assert_eq!(membership.present_vs_expected(&present), (2, 3));
+    }
+
+    #[test]
+    fn disjoint_identity_spaces_never_intersect() {
+        let membership = ExpectedMembership::new(set(&["pod-a", "pod-b"]));
+        let present = set(&["10.0.0.1", "10.0.0.2"]);
+
+        assert_eq!(membership.present_vs_expected(&present), (0, 2));
+    }
+
+    #[test]
+    fn is_expected_checks_membership() {
+        let membership = ExpectedMembership::new(set(&["10.0.0.1"]));
+        assert!(membership.is_expected("10.0.0.1"));
+        assert!(!membership.is_expected("10.0.0.2"));
+    }
+}
+
+/// Queries the Kubernetes API for pods matching a label selector and
+/// turns them into an [`ExpectedMembership`] set. This is a seed, run
+/// once at startup and optionally on a slow refresh interval; it never
+/// participates in liveness decisions.
+pub struct K8sWorkerDiscovery {
+    conf: K8sDiscoveryConf,
+}
+
+impl K8sWorkerDiscovery {
+    pub fn new(conf: K8sDiscoveryConf) -> Self {
+        Self { conf }
+    }
+
+    pub fn from_conf(conf: &ClusterConf) -> Self {
+        Self::new(K8sDiscoveryConf::from_conf(conf))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.conf.enabled
+    }
+
+    /// List pods matching `label_selector` in `namespace` and return their
+    /// identities, keyed by pod IP (not pod name) to match how
+    /// `live_worker_ids()` identifies workers. A pod without an assigned IP
+    /// yet (still `Pending`) is skipped until the next refresh.
+    pub async fn discover(&self) -> FsResult<ExpectedMembership> {
+        if !self.conf.enabled {
+            return Ok(ExpectedMembership::default());
+        }
+
+        let client = kube::Client::try_default()
+            .await
+            .map_err(|e| FsError::common(format!("k8s client init failed: {}", e)))?;
+        let pods: kube::Api<k8s_openapi::api::core::v1::Pod> =
+            kube::Api::namespaced(client, &self.conf.namespace);
+
+        let list_params = kube::api::ListParams::default().labels(&self.conf.label_selector);
+        let pod_list = pods
+            .list(&list_params)
+            .await
+            .map_err(|e| FsError::common(format!("k8s pod list failed: {}", e)))?;
+
+        let expected = pod_list
+            .items
+            .into_iter()
+            .filter_map(|pod| pod.status.and_then(|status| status.pod_ip))
+            .collect();
+
+        Ok(ExpectedMembership::new(expected))
+    }
+
+    /// Spawns a background task that runs `discover` once immediately and
+    /// then on a slow refresh interval, writing each result into the
+    /// shared membership set every `MasterHandler::get_master_info` reads
+    /// from. A disabled discovery backend makes this a no-op.
+    pub fn spawn_seed_task(
+        self: Arc<Self>,
+        membership: Arc<RwLock<ExpectedMembership>>,
+        refresh_interval: Duration,
+        rt: &Runtime,
+    ) {
+        if !self.enabled() {
+            return;
+        }
+        rt.spawn(async move {
+            loop {
+                // A failed refresh leaves the previous membership snapshot
+                // in place rather than clearing it, so a transient API
+                // server hiccup doesn't make every worker look unexpected.
+                if let Ok(discovered) = self.discover().await {
+                    *membership.write() = discovered;
+                }
+                tokio::time::sleep(refresh_interval).await;
+            }
+        });
+    }
+}
@@ -12,8 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::master::batch::{batch_op, BatchOp, BatchOpResult, BatchRequest, BatchResponse};
+use crate::master::checksum::{verify_block_checksum, BlockChecksum, ChecksumAlgorithm};
+use crate::master::dedup::BlockDedupIndex;
+use crate::master::discovery::ExpectedMembership;
 use crate::master::fs::{FsRetryCache, MasterFilesystem, OperationStatus};
 use crate::master::load::{LoadManager, MasterLoadService};
+use crate::master::placement::ReplicaPlacementPolicy;
+use crate::master::resync::{ResyncManager, ResyncReason};
 use crate::master::MountManager;
 use crate::master::{Master, MasterMetrics, RpcContext};
 use curvine_common::conf::ClusterConf;
@@ -29,6 +35,8 @@ use orpc::handler::MessageHandler;
 use orpc::io::net::ConnState;
 use orpc::message::Message;
 use orpc::runtime::Runtime;
+use parking_lot::RwLock;
+use prost::Message as _;
 use std::sync::Arc;
 
 pub struct MasterHandler {
@@ -39,6 +47,11 @@ pub struct MasterHandler {
     pub(crate) conn_state: Option<ConnState>,
     pub(crate) load_service: Option<MasterLoadService>,
     pub(crate) mount_manager: Arc<MountManager>,
+    pub(crate) placement_policy: Arc<ReplicaPlacementPolicy>,
+    pub(crate) resync_manager: Arc<ResyncManager>,
+    pub(crate) expected_membership: Arc<RwLock<ExpectedMembership>>,
+    pub(crate) dedup_index: Arc<BlockDedupIndex>,
+    pub(crate) default_checksum_algorithm: ChecksumAlgorithm,
 }
 
 impl MasterHandler {
@@ -50,6 +63,11 @@ impl MasterHandler {
         mount_manager: Arc<MountManager>,
         load_manager: Arc<LoadManager>,
         rt: Arc<Runtime>,
+        expected_membership: Arc<RwLock<ExpectedMembership>>,
+        // Shared across every connection's handler, like `expected_membership`.
+        resync_manager: Arc<ResyncManager>,
+        // Shared across every connection's handler, like `resync_manager`.
+        dedup_index: Arc<BlockDedupIndex>,
     ) -> Self {
         Self {
             fs,
@@ -59,6 +77,14 @@ impl MasterHandler {
             conn_state,
             load_service: Some(MasterLoadService::new(load_manager, rt.clone())),
             mount_manager,
+            placement_policy: Arc::new(ReplicaPlacementPolicy::from_conf(conf)),
+            resync_manager,
+            expected_membership,
+            dedup_index,
+            default_checksum_algorithm: ChecksumAlgorithm::try_from(
+                conf.master.default_checksum_algorithm.as_str(),
+            )
+            .unwrap_or_default(),
         }
     }
 
@@ -99,7 +125,7 @@ impl MasterHandler {
         &mut self,
         req_id: i64,
         path: String,
-        opts: CreateFileOpts,
+        mut opts: CreateFileOpts,
     ) -> FsResult<FileStatus> {
         if self.check_is_retry(req_id)? {
             // HDFS retries return the results of the last calculation
@@ -109,6 +135,15 @@ impl MasterHandler {
             return self.fs.file_status(&path);
         }
 
+        // Resolve the per-file checksum algorithm now, once, so it is
+        // stored on the file instead of re-derived from a possibly-stale
+        // cluster default on every `add_block`/`complete_file`.
+        let algorithm = BlockChecksum::resolve_algorithm(
+            self.default_checksum_algorithm,
+            opts.checksum_algorithm.as_deref(),
+        )?;
+        opts.checksum_algorithm = Some(algorithm.as_str().to_string());
+
         let res = self.fs.create_with_opts(&path, opts);
         self.set_req_cache(req_id, res)
     }
@@ -152,8 +187,24 @@ impl MasterHandler {
             return Ok(true);
         }
 
+        // Collect the digests of any deduplicated blocks under this path
+        // before they are removed, so their refcounts can be dropped once
+        // the delete actually succeeds (a no-op for non-deduplicated
+        // blocks, which were never registered in the index).
+        let digests = self
+            .fs
+            .block_digests_under(&header.path)
+            .unwrap_or_default();
+
         let res = self.fs.delete(&header.path, header.recursive);
-        self.set_req_cache(req_id, res)
+        let res = self.set_req_cache(req_id, res);
+
+        if matches!(res, Ok(true)) {
+            for digest in digests {
+                self.dedup_index.decrement(&digest);
+            }
+        }
+        res
     }
 
     pub fn retry_check_delete(&mut self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
@@ -170,8 +221,20 @@ impl MasterHandler {
             return Ok(true);
         }
 
+        // A rename that overwrites an existing destination drops that
+        // destination's blocks; collect their digests up front so the
+        // dedup index can release them once the rename actually happens.
+        let overwritten_digests = self.fs.block_digests_under(&header.dst).unwrap_or_default();
+
         let res = self.fs.rename(&header.src, &header.dst);
-        self.set_req_cache(req_id, res)
+        let res = self.set_req_cache(req_id, res);
+
+        if matches!(res, Ok(true)) {
+            for digest in overwritten_digests {
+                self.dedup_index.decrement(&digest);
+            }
+        }
+        res
     }
 
     pub fn retry_check_rename(&mut self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
@@ -197,6 +260,79 @@ impl MasterHandler {
         ctx.response(rep_header)
     }
 
+    // Dispatches a single batched sub-operation through the same
+    // idempotent per-op methods used outside a batch, so a sub-op's
+    // retry-cache entry is keyed by its own `req_id` exactly as if it had
+    // been sent standalone.
+    fn batch_op(&mut self, op: BatchOp) -> BatchOpResult {
+        // `BatchOpResult::payload` is opaque bytes (see `batch.rs`), so
+        // each arm encodes its own response message with `prost` instead
+        // of going through a dedicated `ProtoUtils` conversion the way a
+        // standalone RPC's top-level response does.
+        let result = match op.payload {
+            Some(batch_op::Payload::Mkdir(header)) => {
+                let opts = ProtoUtils::mkdir_opts_from_pb(header.opts);
+                self.fs
+                    .mkdir_with_opts(&header.path, opts)
+                    .map(|flag| MkdirResponse { flag }.encode_to_vec())
+            }
+            Some(batch_op::Payload::CreateFile(header)) => {
+                let opts = ProtoUtils::create_opts_from_pb(header.opts);
+                self.create_file0(op.req_id, header.path, opts)
+                    .map(|status| {
+                        CreateFileResponse {
+                            file_status: ProtoUtils::file_status_to_pb(status),
+                        }
+                        .encode_to_vec()
+                    })
+            }
+            Some(batch_op::Payload::Delete(header)) => self
+                .delete0(op.req_id, header)
+                .map(|_| DeleteResponse::default().encode_to_vec()),
+            Some(batch_op::Payload::Rename(header)) => self
+                .rename0(op.req_id, header)
+                .map(|result| RenameResponse { result }.encode_to_vec()),
+            Some(batch_op::Payload::FileStatus(header)) => {
+                self.fs.file_status(header.path.as_str()).map(|status| {
+                    GetFileStatusResponse {
+                        status: ProtoUtils::file_status_to_pb(status),
+                    }
+                    .encode_to_vec()
+                })
+            }
+            None => Err(FsError::common("batch op missing payload")),
+        };
+
+        match result {
+            Ok(payload) => BatchOpResult {
+                req_id: op.req_id,
+                ok: true,
+                payload,
+                error: String::new(),
+            },
+            Err(e) => BatchOpResult {
+                req_id: op.req_id,
+                ok: false,
+                payload: vec![],
+                error: e.to_string(),
+            },
+        }
+    }
+
+    // Batches a vector of sub-operations into a single round trip. Each
+    // sub-op succeeds or fails independently; a failure never aborts the
+    // rest of the batch, matching the per-op idempotency semantics the
+    // standalone RPCs already provide.
+    pub fn batch(&mut self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
+        let req: BatchRequest = ctx.parse_header()?;
+        ctx.set_audit(Some(format!("batch({} ops)", req.ops.len())), None);
+
+        let results = req.ops.into_iter().map(|op| self.batch_op(op)).collect();
+
+        let rep_header = BatchResponse { results };
+        ctx.response(rep_header)
+    }
+
     // The add block internally determines whether it is a retry request.
     pub fn add_block(&mut self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
         let req: AddBlockRequest = ctx.parse_header()?;
@@ -205,8 +341,50 @@ impl MasterHandler {
         let path = req.path;
         let client_addr = ProtoUtils::client_address_from_pb(req.client_address);
         let previous = req.previous.map(ProtoUtils::commit_block_from_pb);
+        let checksum = req.checksum.map(ProtoUtils::block_checksum_from_pb);
+        let dedup_digest = req.dedup_digest.map(ProtoUtils::chunk_digest_from_pb);
+
+        // A dedup digest hit means this block's content already exists
+        // somewhere in the cluster: reuse its locations instead of asking
+        // `placement_policy` to pick fresh ones, but still go through
+        // `fs.add_block` so `previous` is committed and the reused block is
+        // attached to `path`'s own metadata (with `checksum`, if any) the
+        // same way a freshly allocated block would be.
+        if let Some(digest) = dedup_digest {
+            if let Some(existing) = self.dedup_index.lookup(&digest) {
+                let located_block = self.fs.add_block(
+                    path,
+                    client_addr,
+                    previous,
+                    existing.locations.clone(),
+                    self.placement_policy.clone(),
+                    checksum,
+                )?;
+                self.dedup_index.increment(&digest);
+                let rep_header = ProtoUtils::located_block_to_pb(located_block);
+                return ctx.response(rep_header);
+            }
+        }
+
+        // Block target selection is zone/rack-aware: `fs.add_block` consults
+        // `placement_policy` to spread replicas across distinct fault domains
+        // instead of picking workers without regard to topology. The
+        // optional `checksum` is the client's expected digest for this
+        // block, recorded so a later `block_report` can be verified
+        // against it.
+        let located_block = self.fs.add_block(
+            path,
+            client_addr,
+            previous,
+            vec![],
+            self.placement_policy.clone(),
+            checksum,
+        )?;
+
+        if let Some(digest) = dedup_digest {
+            self.dedup_index.register(digest, located_block.clone());
+        }
 
-        let located_block = self.fs.add_block(path, client_addr, previous, vec![])?;
         let rep_header = ProtoUtils::located_block_to_pb(located_block);
         ctx.response(rep_header)
     }
@@ -217,8 +395,9 @@ impl MasterHandler {
         ctx.set_audit(Some(req.path.to_string()), None);
 
         let last = req.last.map(ProtoUtils::commit_block_from_pb);
+        let last_checksum = req.last_checksum.map(ProtoUtils::block_checksum_from_pb);
         self.fs
-            .complete_file(req.path, req.len, last, req.client_name)?;
+            .complete_file(req.path, req.len, last, last_checksum, req.client_name)?;
         let rep_header = CompleteFileResponse::default();
         ctx.response(rep_header)
     }
@@ -248,6 +427,10 @@ impl MasterHandler {
         let req: GetBlockLocationsRequest = ctx.parse_header()?;
         ctx.set_audit(Some(req.path.to_string()), None);
 
+        // Each returned block carries the checksum recorded at
+        // `complete_file` time (if any), so a reader can verify the bytes
+        // it pulls from a worker without a second round trip to ask for
+        // the digest separately.
         let blocks = self.fs.get_block_locations(req.path)?;
         let rep_header = GetBlockLocationsResponse {
             blocks: ProtoUtils::file_blocks_to_pb(blocks),
@@ -259,32 +442,86 @@ impl MasterHandler {
         let _: GetMasterInfoRequest = ctx.parse_header()?;
 
         let info = self.fs.master_info()?;
-        let rep_header = ProtoUtils::master_info_to_pb(info);
+        let mut rep_header = ProtoUtils::master_info_to_pb(info);
+
+        // If Kubernetes discovery seeded an expected membership set,
+        // surface expected-vs-present worker counts so callers can tell
+        // "not yet reporting" apart from "never existed" instead of
+        // reading the live heartbeat roster as the whole cluster.
+        let membership = self.expected_membership.read();
+        if membership.expected_count() > 0 {
+            let present = self.fs.worker_manager.read().live_worker_ids();
+            let (present_count, expected_count) = membership.present_vs_expected(&present);
+            rep_header.expected_worker_count = expected_count as i32;
+            rep_header.present_worker_count = present_count as i32;
+        }
+
         ctx.response(rep_header)
     }
 
     pub fn worker_heartbeat(&self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
         let header: WorkerHeartbeatRequest = ctx.parse_header()?;
+        let address = ProtoUtils::worker_address_from_pb(&header.address);
         let mut wm = self.fs.worker_manager.write();
 
+        // The worker's zone/rack tags ride along on every heartbeat so
+        // `WorkerManager` can keep each worker's `PlacementCandidate` up to
+        // date without a separate registration step; a worker that never
+        // reports a tag is treated as untagged, the same as before this
+        // field existed.
         let cmds = wm.heartbeat(
             &header.cluster_id,
             HeartbeatStatus::from(header.status),
-            ProtoUtils::worker_address_from_pb(&header.address),
+            address.clone(),
             ProtoUtils::storage_info_list_from_pb(header.storages),
+            header.zone.clone(),
+            header.rack.clone(),
         )?;
         drop(wm);
 
-        let rep_header = WorkerHeartbeatResponse {
-            cmds: ProtoUtils::worker_cmd_to_pb(cmds),
-        };
+        // Fold in any copy/delete commands the resync drain loop has
+        // staged for this worker since its last heartbeat, so healing
+        // actually rides back to workers instead of sitting in the queue.
+        let resync_cmds = self.resync_manager.drain_commands_for(&address);
+        let mut pb_cmds = ProtoUtils::worker_cmd_to_pb(cmds);
+        pb_cmds.extend(ProtoUtils::resync_cmd_to_pb(resync_cmds));
+
+        let rep_header = WorkerHeartbeatResponse { cmds: pb_cmds };
         ctx.response(rep_header)
     }
 
     pub fn block_report(&self, ctx: &mut RpcContext<'_>) -> FsResult<Message> {
         let header: BlockReportListRequest = ctx.parse_header()?;
+
+        // Compare each reported digest against the digest recorded at
+        // `complete_file` time before folding the batch into
+        // `fs.block_report`, so a silently corrupted replica is queued for
+        // resync even when its replica count otherwise looks healthy.
+        for report in &header.reports {
+            let expected = self.fs.get_block_checksum(report.block_id)?;
+            let reported = report
+                .checksum
+                .clone()
+                .map(ProtoUtils::block_checksum_from_pb);
+            if verify_block_checksum(expected.as_ref(), reported.as_ref()).is_err() {
+                self.resync_manager
+                    .enqueue(report.block_id, ResyncReason::Corrupt, 1);
+            }
+        }
+
         let list = ProtoUtils::block_report_list_from_pb(header);
-        self.fs.block_report(list)?;
+        let degraded = self.fs.block_report(list)?;
+
+        // Any block the report revealed as under/over-replicated or
+        // corrupt is handed to the resync manager so the background
+        // healing task picks it up on its next drain.
+        for block in degraded {
+            self.resync_manager
+                .enqueue(block.block_id, block.reason, block.distance);
+        }
+        self.metrics
+            .resync_queue_depth
+            .set(self.resync_manager.queue_depth() as i64);
 
         let rep_header = BlockReportListResponse::default();
         ctx.response(rep_header)
@@ -412,6 +649,7 @@ impl MessageHandler for MasterHandler {
             RpcCode::Delete => self.retry_check_delete(ctx),
             RpcCode::Rename => self.retry_check_rename(ctx),
             RpcCode::ListStatus => self.list_status(ctx),
+            RpcCode::Batch => self.batch(ctx),
             RpcCode::GetBlockLocations => self.get_block_locations(ctx),
             RpcCode::SetAttr => self.set_attr_retry_check(ctx),
             RpcCode::Symlink => self.symlink_retry_check(ctx),
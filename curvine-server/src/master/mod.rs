@@ -0,0 +1,41 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+mod master;
+mod metrics;
+mod mount;
+mod rpc_context;
+
+pub mod admin_api;
+pub mod batch;
+pub mod checksum;
+pub mod dedup;
+pub mod discovery;
+pub mod fs;
+pub mod load;
+pub mod master_handler;
+pub mod placement;
+pub mod resync;
+
+pub use admin_api::AdminApiServer;
+pub use checksum::{BlockChecksum, ChecksumAlgorithm};
+pub use dedup::{BlockDedupIndex, ChunkDigest, ContentDefinedChunker};
+pub use discovery::{ExpectedMembership, K8sWorkerDiscovery};
+pub use master::Master;
+pub use master_handler::MasterHandler;
+pub use metrics::MasterMetrics;
+pub use mount::MountManager;
+pub use placement::{ReplicaPlacementPolicy, SpreadPolicy};
+pub use resync::ResyncManager;
+pub use rpc_context::RpcContext;
@@ -0,0 +1,267 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Zone/rack-aware replica placement: candidates are grouped by fault
+//! domain (zone), zones are visited in order of descending free capacity,
+//! and at most one worker per zone is picked per pass.
+
+use curvine_common::conf::ClusterConf;
+use curvine_common::error::FsError;
+use curvine_common::state::WorkerAddress;
+use curvine_common::FsResult;
+use std::collections::HashMap;
+
+/// How strictly the placement policy enforces zone spread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadPolicy {
+    /// Fail the allocation rather than placing two replicas in the same
+    /// zone when there are fewer zones than replicas.
+    Strict,
+
+    /// Prefer spreading across zones, but fall back to doubling up within
+    /// a zone if that is the only way to reach the target replica count.
+    BestEffort,
+}
+
+impl Default for SpreadPolicy {
+    fn default() -> Self {
+        SpreadPolicy::BestEffort
+    }
+}
+
+impl From<&str> for SpreadPolicy {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "strict" => SpreadPolicy::Strict,
+            _ => SpreadPolicy::BestEffort,
+        }
+    }
+}
+
+/// Minimal view of a worker that the placement policy needs in order to
+/// rank and select candidates. `WorkerManager` builds this from its live
+/// worker table on every `add_block` call.
+#[derive(Debug, Clone)]
+pub struct PlacementCandidate {
+    pub address: WorkerAddress,
+    pub zone: Option<String>,
+    pub rack: Option<String>,
+    pub available_capacity: i64,
+}
+
+/// Greedy, zone-aware replica target selector: no two replicas land in the
+/// same zone unless unavoidable.
+pub struct ReplicaPlacementPolicy {
+    spread_policy: SpreadPolicy,
+}
+
+impl ReplicaPlacementPolicy {
+    pub fn new(spread_policy: SpreadPolicy) -> Self {
+        Self { spread_policy }
+    }
+
+    pub fn from_conf(conf: &ClusterConf) -> Self {
+        Self::new(conf.master.replica_spread_policy.as_str().into())
+    }
+
+    pub fn spread_policy(&self) -> SpreadPolicy {
+        self.spread_policy
+    }
+
+    /// Select up to `num_replicas` workers from `candidates`, excluding any
+    /// worker whose address appears in `exclude` or that has no free
+    /// capacity (`available_capacity <= 0`).
+    ///
+    /// Workers are grouped by fault domain (zone, falling back to rack,
+    /// falling back to a per-worker singleton domain), domains are sorted
+    /// by descending total free capacity, and one worker per domain is
+    /// taken per round-robin pass. Under [`SpreadPolicy::Strict`], an
+    /// `Err` is returned instead of a partial result when fewer than
+    /// `num_replicas` distinct domains are available.
+    pub fn select_targets(
+        &self,
+        candidates: &[PlacementCandidate],
+        num_replicas: usize,
+        exclude: &[WorkerAddress],
+    ) -> FsResult<Vec<PlacementCandidate>> {
+        if num_replicas == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut zones: HashMap<String, Vec<&PlacementCandidate>> = HashMap::new();
+        for candidate in candidates {
+            if exclude.contains(&candidate.address) {
+                continue;
+            }
+            if candidate.available_capacity <= 0 {
+                continue;
+            }
+            let zone_key = candidate
+                .zone
+                .clone()
+                .or_else(|| candidate.rack.clone().map(|rack| format!("rack:{}", rack)))
+                .unwrap_or_else(|| format!("__solo__{}", candidate.address));
+            zones.entry(zone_key).or_default().push(candidate);
+        }
+
+        for workers in zones.values_mut() {
+            workers.sort_by(|a, b| b.available_capacity.cmp(&a.available_capacity));
+        }
+
+        let mut zone_order: Vec<String> = zones.keys().cloned().collect();
+        zone_order.sort_by(|a, b| {
+            let cap_a: i64 = zones[a].iter().map(|w| w.available_capacity).sum();
+            let cap_b: i64 = zones[b].iter().map(|w| w.available_capacity).sum();
+            cap_b.cmp(&cap_a)
+        });
+
+        let mut cursors: HashMap<String, usize> = HashMap::new();
+        let mut selected = Vec::with_capacity(num_replicas);
+
+        'rounds: loop {
+            let mut picked_this_round = false;
+            for zone in &zone_order {
+                if selected.len() >= num_replicas {
+                    break 'rounds;
+                }
+                let workers = &zones[zone];
+                let cursor = cursors.entry(zone.clone()).or_insert(0);
+                if let Some(candidate) = workers.get(*cursor) {
+                    selected.push((*candidate).clone());
+                    *cursor += 1;
+                    picked_this_round = true;
+                }
+            }
+            if !picked_this_round {
+                // Every zone has been exhausted; nothing more to offer.
+                break;
+            }
+            if self.spread_policy == SpreadPolicy::Strict && selected.len() < num_replicas {
+                // A strict policy stops after one full spread pass rather
+                // than doubling up within a zone.
+                break;
+            }
+        }
+
+        if self.spread_policy == SpreadPolicy::Strict && selected.len() < num_replicas {
+            return Err(FsError::common(format!(
+                "strict spread policy could not place {} replicas across distinct fault domains, only found {}",
+                num_replicas,
+                selected.len()
+            )));
+        }
+
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidate(
+        addr: &str,
+        zone: Option<&str>,
+        rack: Option<&str>,
+        capacity: i64,
+    ) -> PlacementCandidate {
+        PlacementCandidate {
+            address: WorkerAddress::new(addr.to_string(), 0),
+            zone: zone.map(str::to_string),
+            rack: rack.map(str::to_string),
+            available_capacity: capacity,
+        }
+    }
+
+    #[test]
+    fn spreads_across_zones_before_doubling_up() {
+        let candidates = vec![
+            candidate("w1", Some("z1"), None, 100),
+            candidate("w2", Some("z1"), None, 50),
+            candidate("w3", Some("z2"), None, 10),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::BestEffort);
+
+        let selected = policy.select_targets(&candidates, 2, &[]).unwrap();
+        let zones: Vec<_> = selected.iter().map(|c| c.zone.clone()).collect();
+        assert_eq!(zones, vec![Some("z1".to_string()), Some("z2".to_string())]);
+    }
+
+    #[test]
+    fn falls_back_to_rack_when_zone_is_unset() {
+        let candidates = vec![
+            candidate("w1", None, Some("r1"), 100),
+            candidate("w2", None, Some("r1"), 90),
+            candidate("w3", None, Some("r2"), 10),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::BestEffort);
+
+        let selected = policy.select_targets(&candidates, 2, &[]).unwrap();
+        let racks: Vec<_> = selected.iter().map(|c| c.rack.clone()).collect();
+        assert_eq!(racks, vec![Some("r1".to_string()), Some("r2".to_string())]);
+    }
+
+    #[test]
+    fn best_effort_doubles_up_when_zones_are_exhausted() {
+        let candidates = vec![
+            candidate("w1", Some("z1"), None, 100),
+            candidate("w2", Some("z1"), None, 50),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::BestEffort);
+
+        let selected = policy.select_targets(&candidates, 2, &[]).unwrap();
+        assert_eq!(selected.len(), 2);
+    }
+
+    #[test]
+    fn strict_errors_instead_of_doubling_up() {
+        let candidates = vec![
+            candidate("w1", Some("z1"), None, 100),
+            candidate("w2", Some("z1"), None, 50),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::Strict);
+
+        let err = policy.select_targets(&candidates, 2, &[]);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn excludes_workers_with_no_free_capacity() {
+        let candidates = vec![
+            candidate("w1", Some("z1"), None, 0),
+            candidate("w2", Some("z1"), None, -5),
+            candidate("w3", Some("z2"), None, 10),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::BestEffort);
+
+        let selected = policy.select_targets(&candidates, 2, &[]).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].address, WorkerAddress::new("w3".to_string(), 0));
+    }
+
+    #[test]
+    fn excludes_already_used_workers() {
+        let candidates = vec![
+            candidate("w1", Some("z1"), None, 100),
+            candidate("w2", Some("z2"), None, 50),
+        ];
+        let policy = ReplicaPlacementPolicy::new(SpreadPolicy::BestEffort);
+
+        let selected = policy
+            .select_targets(&candidates, 1, &[WorkerAddress::new("w1".to_string(), 0)])
+            .unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].address, WorkerAddress::new("w2".to_string(), 0));
+    }
+}
@@ -0,0 +1,589 @@
+// Copyright 2025 OPPO.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background re-replication queue: blocks that drift away from their
+//! target replication factor are queued here, keyed by how far they are
+//! from their target, and a background task drains the queue at a
+//! configurable throttle ("tranquility") issuing copy/delete commands
+//! that ride back to workers on `WorkerHeartbeatResponse::cmds`.
+
+use crate::master::placement::{PlacementCandidate, ReplicaPlacementPolicy};
+use curvine_common::conf::ClusterConf;
+use curvine_common::state::WorkerAddress;
+use orpc::runtime::Runtime;
+use parking_lot::Mutex;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Why a block was queued for resync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResyncReason {
+    /// Fewer live replicas than the target replication factor.
+    UnderReplicated,
+    /// More live replicas than the target replication factor.
+    OverReplicated,
+    /// A `block_report` digest mismatch marked a replica corrupt.
+    Corrupt,
+}
+
+/// A single block's distance from its desired replication state.
+#[derive(Debug, Clone)]
+pub struct ResyncTask {
+    pub block_id: i64,
+    pub reason: ResyncReason,
+    /// abs(current_replicas - target_replicas); higher sorts first.
+    pub distance: u32,
+    pub retry_count: u32,
+    pub next_attempt: Instant,
+}
+
+impl ResyncTask {
+    pub fn new(block_id: i64, reason: ResyncReason, distance: u32) -> Self {
+        Self {
+            block_id,
+            reason,
+            distance,
+            retry_count: 0,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    /// Exponential backoff so a permanently unresolvable block doesn't
+    /// spin the queue: 1s, 2s, 4s, ... capped at five minutes.
+    fn backoff(retry_count: u32) -> Duration {
+        let secs = 1u64 << retry_count.min(9);
+        Duration::from_secs(secs.min(300))
+    }
+
+    fn reschedule(&mut self) {
+        self.retry_count += 1;
+        self.next_attempt = Instant::now() + Self::backoff(self.retry_count);
+    }
+}
+
+impl PartialEq for ResyncTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance && self.block_id == other.block_id
+    }
+}
+impl Eq for ResyncTask {}
+
+impl PartialOrd for ResyncTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ResyncTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap; the most-degraded block should pop first.
+        self.distance.cmp(&other.distance)
+    }
+}
+
+/// A copy or delete instruction produced for a drained resync task. The
+/// caller folds these into the `cmds` field of `WorkerHeartbeatResponse`.
+#[derive(Debug, Clone)]
+pub enum ResyncCommand {
+    Copy {
+        block_id: i64,
+        target: WorkerAddress,
+    },
+    Delete {
+        block_id: i64,
+        source: WorkerAddress,
+    },
+}
+
+/// Decides what a drained [`ResyncTask`] should turn into: a copy to a
+/// worker with spare zone-aware capacity, a delete of a now-redundant
+/// replica, or nothing yet (e.g. no eligible target is currently live).
+/// Implemented by whatever owns the live worker/placement state
+/// (`MasterFilesystem`), so this module stays ignorant of that state and
+/// only drives the queue/backoff/dispatch mechanics.
+pub trait ResyncPlanner: Send + Sync {
+    /// Returns `Some((command, destination))` when the task can be acted
+    /// on right now, or `None` to requeue it with backoff (e.g. because no
+    /// healthy target is currently available).
+    fn plan(&self, task: &ResyncTask) -> Option<(ResyncCommand, WorkerAddress)>;
+}
+
+/// Read-only cluster state a [`ResyncPlanner`] needs: where a block's
+/// replicas currently live, and which workers are eligible copy targets.
+/// `MasterFilesystem` implements this over its live worker table.
+pub trait ResyncClusterView: Send + Sync {
+    /// Workers currently holding a live replica of `block_id`.
+    fn live_replicas(&self, block_id: i64) -> Vec<WorkerAddress>;
+
+    /// Workers the placement policy may pick a new copy target from.
+    fn placement_candidates(&self) -> Vec<PlacementCandidate>;
+}
+
+/// The concrete [`ResyncPlanner`]: copies under-replicated/corrupt blocks
+/// to a zone-aware target excluding current holders, and deletes the
+/// least-preferred holder of an over-replicated block.
+pub struct CapacityAwareResyncPlanner {
+    view: Arc<dyn ResyncClusterView>,
+    placement_policy: Arc<ReplicaPlacementPolicy>,
+}
+
+impl CapacityAwareResyncPlanner {
+    pub fn new(
+        view: Arc<dyn ResyncClusterView>,
+        placement_policy: Arc<ReplicaPlacementPolicy>,
+    ) -> Self {
+        Self {
+            view,
+            placement_policy,
+        }
+    }
+}
+
+impl ResyncPlanner for CapacityAwareResyncPlanner {
+    fn plan(&self, task: &ResyncTask) -> Option<(ResyncCommand, WorkerAddress)> {
+        let holders = self.view.live_replicas(task.block_id);
+
+        match task.reason {
+            ResyncReason::OverReplicated => {
+                if holders.is_empty() {
+                    return None;
+                }
+                // Score each holder by its current available capacity (a
+                // holder not currently a placement candidate, e.g. it has
+                // dropped out of the live worker table, scores lowest and
+                // is preferred for deletion first). The least-preferred
+                // holder to keep is the one with the least available
+                // capacity, so deleting it relieves the most pressure.
+                let capacity_by_address: HashMap<WorkerAddress, i64> = self
+                    .view
+                    .placement_candidates()
+                    .into_iter()
+                    .map(|c| (c.address, c.available_capacity))
+                    .collect();
+                let source = holders
+                    .iter()
+                    .min_by_key(|addr| capacity_by_address.get(*addr).copied().unwrap_or(i64::MIN))
+                    .cloned()?;
+                Some((
+                    ResyncCommand::Delete {
+                        block_id: task.block_id,
+                        source: source.clone(),
+                    },
+                    source,
+                ))
+            }
+            ResyncReason::UnderReplicated | ResyncReason::Corrupt => {
+                let candidates = self.view.placement_candidates();
+                let target = self
+                    .placement_policy
+                    .select_targets(&candidates, 1, &holders)
+                    .ok()?
+                    .into_iter()
+                    .next()?;
+                Some((
+                    ResyncCommand::Copy {
+                        block_id: task.block_id,
+                        target: target.address.clone(),
+                    },
+                    target.address,
+                ))
+            }
+        }
+    }
+}
+
+/// Throttle applied between drains of the resync queue, equivalent to
+/// Garage's "background tranquility" knob: higher values slow healing
+/// down to leave more headroom for foreground traffic.
+#[derive(Debug, Clone, Copy)]
+pub struct Tranquility(pub u32);
+
+impl Tranquility {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(100 * (self.0.max(1) as u64))
+    }
+}
+
+/// Priority queue of blocks awaiting re-replication, plus the knobs that
+/// govern how fast it is drained. Owned once by `Master` and shared with
+/// every `MasterHandler` as an `Arc`, like `expected_membership`.
+pub struct ResyncManager {
+    queue: Mutex<BinaryHeap<ResyncTask>>,
+    /// Backoff state for blocks whose task was dropped after exhausting
+    /// `max_retries`, keyed by block id. Consulted by `enqueue` so a block
+    /// that keeps reappearing in block reports after being dropped resumes
+    /// its backoff instead of restarting at `retry_count = 0`.
+    dropped: Mutex<HashMap<i64, (u32, Instant)>>,
+    /// Commands a drain pass has produced but that haven't yet ridden
+    /// back to their target worker on a heartbeat response.
+    outbox: Mutex<HashMap<WorkerAddress, Vec<ResyncCommand>>>,
+    tranquility: Tranquility,
+    max_retries: u32,
+}
+
+impl ResyncManager {
+    pub fn new(tranquility: Tranquility, max_retries: u32) -> Self {
+        Self {
+            queue: Mutex::new(BinaryHeap::new()),
+            dropped: Mutex::new(HashMap::new()),
+            outbox: Mutex::new(HashMap::new()),
+            tranquility,
+            max_retries,
+        }
+    }
+
+    pub fn from_conf(conf: &ClusterConf) -> Self {
+        Self::new(
+            Tranquility(conf.master.resync_tranquility),
+            conf.master.resync_max_retries,
+        )
+    }
+
+    /// Queue (or re-prioritize) a block discovered to be under/over
+    /// replicated or corrupt.
+    ///
+    /// If the block is already queued, its accumulated `retry_count` and
+    /// `next_attempt` backoff carry over to the new task rather than being
+    /// reset. If the block was previously dropped from the queue after
+    /// exhausting `max_retries`, its backoff carries over from `dropped`
+    /// instead: a block that keeps reappearing in block reports (because it
+    /// is permanently unresolvable, e.g. every replica is gone) must still
+    /// stay backed off instead of restarting at `retry_count = 0` on the
+    /// very next report.
+    pub fn enqueue(&self, block_id: i64, reason: ResyncReason, distance: u32) {
+        if distance == 0 {
+            return;
+        }
+        let mut queue = self.queue.lock();
+        let mut existing = None;
+        queue.retain(|t| {
+            if t.block_id == block_id {
+                existing = Some((t.retry_count, t.next_attempt));
+                false
+            } else {
+                true
+            }
+        });
+        if existing.is_none() {
+            existing = self.dropped.lock().remove(&block_id);
+        }
+
+        let mut task = ResyncTask::new(block_id, reason, distance);
+        if let Some((retry_count, next_attempt)) = existing {
+            task.retry_count = retry_count;
+            task.next_attempt = next_attempt;
+        }
+        queue.push(task);
+    }
+
+    /// Pop the single most-degraded block whose backoff has elapsed, if
+    /// any. Tasks still backing off are left in the queue.
+    pub fn pop_ready(&self) -> Option<ResyncTask> {
+        let mut queue = self.queue.lock();
+        let mut deferred = Vec::new();
+        let mut ready = None;
+        let now = Instant::now();
+
+        while let Some(task) = queue.pop() {
+            if task.next_attempt <= now {
+                ready = Some(task);
+                break;
+            }
+            deferred.push(task);
+        }
+        for task in deferred {
+            queue.push(task);
+        }
+        ready
+    }
+
+    /// Requeue a task that failed to apply, applying exponential backoff.
+    /// Once `max_retries` is exceeded the task is dropped from the queue so
+    /// a permanently unresolvable block stops consuming queue cycles, but
+    /// its backoff state is kept in `dropped` so a later `enqueue` for the
+    /// same block resumes the backoff instead of spinning at
+    /// `retry_count = 0`.
+    pub fn requeue_failed(&self, mut task: ResyncTask) {
+        if task.retry_count >= self.max_retries {
+            self.dropped
+                .lock()
+                .insert(task.block_id, (task.retry_count, task.next_attempt));
+            return;
+        }
+        task.reschedule();
+        self.queue.lock().push(task);
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue.lock().len()
+    }
+
+    pub fn tranquility(&self) -> Tranquility {
+        self.tranquility
+    }
+
+    /// Drain every command queued for `address` so it can be folded into
+    /// that worker's next `WorkerHeartbeatResponse::cmds`.
+    pub fn drain_commands_for(&self, address: &WorkerAddress) -> Vec<ResyncCommand> {
+        self.outbox.lock().remove(address).unwrap_or_default()
+    }
+
+    fn push_command(&self, target: WorkerAddress, cmd: ResyncCommand) {
+        self.outbox.lock().entry(target).or_default().push(cmd);
+    }
+
+    /// One drain pass: pop the most-degraded ready task, ask `planner`
+    /// what to do about it, and either stage the resulting command in the
+    /// outbox or requeue the task with backoff if no target is currently
+    /// available. Returns `true` if a task was processed, so the caller
+    /// can decide whether to keep draining before the next `tranquility`
+    /// sleep.
+    fn drain_one(&self, planner: &dyn ResyncPlanner) -> bool {
+        let Some(task) = self.pop_ready() else {
+            return false;
+        };
+
+        match planner.plan(&task) {
+            Some((cmd, destination)) => self.push_command(destination, cmd),
+            None => self.requeue_failed(task),
+        }
+        true
+    }
+
+    /// Spawns the background task that drains the queue at the configured
+    /// `tranquility` throttle, dispatching copy/delete commands via
+    /// `planner` until the process shuts down. `Master` calls this exactly
+    /// once at startup, right after building the `Arc<ResyncManager>` and
+    /// `Arc<CapacityAwareResyncPlanner>` it then hands to every
+    /// `MasterHandler::new` — not from `MasterHandler::new` itself, which
+    /// runs once per connection and would spawn a duplicate drain loop per
+    /// connection otherwise.
+    pub fn spawn_drain_loop(self: Arc<Self>, planner: Arc<dyn ResyncPlanner>, rt: &Runtime) {
+        let interval = self.tranquility().interval();
+        rt.spawn(async move {
+            loop {
+                // Drain everything currently ready before sleeping, so a
+                // burst of reports doesn't wait a full interval per task.
+                while self.drain_one(planner.as_ref()) {}
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pops_most_degraded_block_first() {
+        let manager = ResyncManager::new(Tranquility(1), 5);
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+        manager.enqueue(2, ResyncReason::UnderReplicated, 5);
+        manager.enqueue(3, ResyncReason::UnderReplicated, 3);
+
+        assert_eq!(manager.pop_ready().unwrap().block_id, 2);
+        assert_eq!(manager.pop_ready().unwrap().block_id, 3);
+        assert_eq!(manager.pop_ready().unwrap().block_id, 1);
+        assert!(manager.pop_ready().is_none());
+    }
+
+    #[test]
+    fn enqueue_preserves_backoff_for_a_reappearing_block() {
+        let manager = ResyncManager::new(Tranquility(1), 5);
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+
+        // Simulate a few failed drain attempts accumulating backoff.
+        let mut task = manager.pop_ready().unwrap();
+        task.reschedule();
+        task.reschedule();
+        assert_eq!(task.retry_count, 2);
+        // requeue_failed itself records one more failed attempt.
+        manager.requeue_failed(task);
+        let next_attempt = manager.queue.lock().peek().unwrap().next_attempt;
+
+        // The block reappears in a later block_report while still backing
+        // off; its retry_count/next_attempt must survive, not reset.
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+
+        let mut queue = manager.queue.lock();
+        let requeued = queue.pop().unwrap();
+        assert_eq!(requeued.retry_count, 3);
+        assert_eq!(requeued.next_attempt, next_attempt);
+    }
+
+    #[test]
+    fn requeue_failed_drops_task_past_max_retries() {
+        let manager = ResyncManager::new(Tranquility(1), 1);
+        let mut task = ResyncTask::new(1, ResyncReason::UnderReplicated, 1);
+        task.retry_count = 1;
+        manager.requeue_failed(task);
+        assert_eq!(manager.queue_depth(), 0);
+    }
+
+    struct AlwaysCopy(WorkerAddress);
+    impl ResyncPlanner for AlwaysCopy {
+        fn plan(&self, task: &ResyncTask) -> Option<(ResyncCommand, WorkerAddress)> {
+            Some((
+                ResyncCommand::Copy {
+                    block_id: task.block_id,
+                    target: self.0.clone(),
+                },
+                self.0.clone(),
+            ))
+        }
+    }
+
+    #[test]
+    fn drain_one_stages_a_command_in_the_outbox() {
+        let manager = ResyncManager::new(Tranquility(1), 5);
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+        let target = WorkerAddress::new("w1".to_string(), 0);
+
+        assert!(manager.drain_one(&AlwaysCopy(target.clone())));
+        let cmds = manager.drain_commands_for(&target);
+        assert_eq!(cmds.len(), 1);
+        assert!(manager.drain_commands_for(&target).is_empty());
+    }
+
+    struct NeverReady;
+    impl ResyncPlanner for NeverReady {
+        fn plan(&self, _task: &ResyncTask) -> Option<(ResyncCommand, WorkerAddress)> {
+            None
+        }
+    }
+
+    #[test]
+    fn drain_one_requeues_with_backoff_when_no_target_is_available() {
+        let manager = ResyncManager::new(Tranquility(1), 5);
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+
+        assert!(manager.drain_one(&NeverReady));
+        assert_eq!(manager.queue_depth(), 1);
+        assert!(manager.pop_ready().is_none());
+    }
+
+    struct FixedView {
+        holders: Vec<WorkerAddress>,
+        candidates: Vec<PlacementCandidate>,
+    }
+
+    impl ResyncClusterView for FixedView {
+        fn live_replicas(&self, _block_id: i64) -> Vec<WorkerAddress> {
+            self.holders.clone()
+        }
+
+        fn placement_candidates(&self) -> Vec<PlacementCandidate> {
+            self.candidates.clone()
+        }
+    }
+
+    fn candidate(addr: &str, capacity: i64) -> PlacementCandidate {
+        PlacementCandidate {
+            address: WorkerAddress::new(addr.to_string(), 0),
+            zone: None,
+            rack: None,
+            available_capacity: capacity,
+        }
+    }
+
+    #[test]
+    fn capacity_aware_planner_copies_under_replicated_block_to_a_new_target() {
+        let holder = WorkerAddress::new("w1".to_string(), 0);
+        let view = Arc::new(FixedView {
+            holders: vec![holder.clone()],
+            candidates: vec![candidate("w1", 100), candidate("w2", 50)],
+        });
+        let planner = CapacityAwareResyncPlanner::new(
+            view,
+            Arc::new(ReplicaPlacementPolicy::new(
+                crate::master::placement::SpreadPolicy::BestEffort,
+            )),
+        );
+
+        let task = ResyncTask::new(1, ResyncReason::UnderReplicated, 1);
+        let (cmd, target) = planner.plan(&task).unwrap();
+        assert_eq!(target, WorkerAddress::new("w2".to_string(), 0));
+        assert!(matches!(cmd, ResyncCommand::Copy { .. }));
+    }
+
+    #[test]
+    fn capacity_aware_planner_deletes_the_holder_with_the_least_available_capacity() {
+        let holders = vec![
+            WorkerAddress::new("w1".to_string(), 0),
+            WorkerAddress::new("w2".to_string(), 0),
+        ];
+        let view = Arc::new(FixedView {
+            holders: holders.clone(),
+            candidates: vec![candidate("w1", 100), candidate("w2", 10)],
+        });
+        let planner = CapacityAwareResyncPlanner::new(
+            view,
+            Arc::new(ReplicaPlacementPolicy::new(
+                crate::master::placement::SpreadPolicy::BestEffort,
+            )),
+        );
+
+        let task = ResyncTask::new(1, ResyncReason::OverReplicated, 1);
+        let (cmd, target) = planner.plan(&task).unwrap();
+        assert_eq!(target, holders[1]);
+        assert!(matches!(cmd, ResyncCommand::Delete { .. }));
+    }
+
+    #[test]
+    fn capacity_aware_planner_prefers_deleting_a_holder_that_dropped_out_of_candidates() {
+        let holders = vec![
+            WorkerAddress::new("w1".to_string(), 0),
+            WorkerAddress::new("w2".to_string(), 0),
+        ];
+        let view = Arc::new(FixedView {
+            holders: holders.clone(),
+            // w2 no longer shows up as a placement candidate at all (e.g.
+            // it dropped off the live worker table); it must still be
+            // preferred for deletion over a holder with known capacity.
+            candidates: vec![candidate("w1", 10)],
+        });
+        let planner = CapacityAwareResyncPlanner::new(
+            view,
+            Arc::new(ReplicaPlacementPolicy::new(
+                crate::master::placement::SpreadPolicy::BestEffort,
+            )),
+        );
+
+        let task = ResyncTask::new(1, ResyncReason::OverReplicated, 1);
+        let (cmd, target) = planner.plan(&task).unwrap();
+        assert_eq!(target, holders[1]);
+        assert!(matches!(cmd, ResyncCommand::Delete { .. }));
+    }
+
+    #[test]
+    fn enqueue_preserves_backoff_for_a_block_dropped_past_max_retries() {
+        let manager = ResyncManager::new(Tranquility(1), 1);
+        let mut task = ResyncTask::new(1, ResyncReason::UnderReplicated, 1);
+        task.retry_count = 1;
+        let next_attempt = task.next_attempt;
+        manager.requeue_failed(task);
+        assert_eq!(manager.queue_depth(), 0);
+
+        // The block reappears in the next block_report even though its
+        // task was dropped; it must resume backoff instead of restarting
+        // at retry_count = 0.
+        manager.enqueue(1, ResyncReason::UnderReplicated, 1);
+        let requeued = manager.queue.lock().pop().unwrap();
+        assert_eq!(requeued.retry_count, 1);
+        assert_eq!(requeued.next_attempt, next_attempt);
+    }
+}